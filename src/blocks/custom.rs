@@ -1,17 +1,26 @@
+use std::collections::HashMap;
 use std::env;
 use std::iter::{Cycle, Peekable};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use std::vec;
 
 use async_trait::async_trait;
 use crossbeam_channel::Sender;
+use dbus::blocking::Connection;
+use dbus_crossroads::Crossroads;
+use rand::Rng;
 use serde_derive::Deserialize;
+use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_update;
 use crate::errors::*;
+use crate::formatting::value::Value;
+use crate::formatting::FormatTemplate;
 use crate::protocol::i3bar_event::I3BarEvent;
 use crate::scheduler::Task;
 use crate::signals::convert_to_valid_signal;
@@ -21,6 +30,7 @@ use crate::widgets::{I3BarWidget, State};
 
 pub struct Custom {
     id: usize,
+    base_interval: Update,
     update_interval: Update,
     command: Option<String>,
     on_click: Option<String>,
@@ -30,6 +40,19 @@ pub struct Custom {
     pub json: bool,
     hide_when_empty: bool,
     shell: String,
+    persistent: bool,
+    watch_output: Option<Arc<Mutex<String>>>,
+    timeout: Option<Duration>,
+    retry_base: Duration,
+    retry_max: Duration,
+    attempt: u32,
+    format: FormatTemplate,
+    threshold_key: Option<String>,
+    good: Option<f64>,
+    info: Option<f64>,
+    warning: Option<f64>,
+    critical: Option<f64>,
+    pushed: Option<Arc<Mutex<PushedState>>>,
     shared_config: SharedConfig,
 }
 
@@ -54,6 +77,49 @@ pub struct CustomConfig {
 
     pub hide_when_empty: bool,
 
+    /// Run `command` once as a long-running process instead of polling it
+    /// every `interval`, and update the block every time it prints a new
+    /// line to stdout. Useful for commands like `tail -f` or `journalctl -f`
+    /// that are meant to be followed rather than repeatedly invoked.
+    pub persistent: bool,
+
+    /// Maximum time (in seconds) to let `command` run before treating it as
+    /// failed. Unset by default so existing configs whose command legitimately
+    /// runs long aren't suddenly flipped to `State::Critical`.
+    pub timeout: Option<u64>,
+
+    /// Initial delay (in seconds) before retrying a failed or timed out
+    /// command, doubled after every consecutive failure
+    pub retry_base: u64,
+
+    /// Upper bound (in seconds) on the retry delay
+    pub retry_max: u64,
+
+    /// Format string used to render the values from a JSON `values` map.
+    /// Defaults to `{text}`. A `|` splits off a short variant to use when
+    /// the bar is constrained for space, e.g. `"{cpu}% {mem}%|{cpu}%"`
+    pub format: FormatTemplate,
+
+    /// Name of a `values` entry used to pick the widget's `State` through
+    /// `good`/`info`/`warning`/`critical` when the script doesn't set
+    /// `state` itself, mirroring the `github` block
+    pub threshold_key: Option<String>,
+
+    pub good: Option<f64>,
+    pub info: Option<f64>,
+    pub warning: Option<f64>,
+    pub critical: Option<f64>,
+
+    /// DBus bus name to register (e.g. `rs.i3status.custom.foo`) so an
+    /// external program can push `{icon, state, text}` updates through the
+    /// `Update` method on the `/Update` object, in addition to or instead
+    /// of polling `command`
+    pub dbus_name: Option<String>,
+
+    /// Clear a pushed update after this many seconds if no newer one
+    /// arrives in the meantime
+    pub clear_after: Option<u64>,
+
     // TODO make a global config option
     pub shell: String,
 }
@@ -67,6 +133,18 @@ impl Default for CustomConfig {
             signal: None,
             json: false,
             hide_when_empty: false,
+            persistent: false,
+            timeout: None,
+            retry_base: 1,
+            retry_max: 300,
+            format: default_format(),
+            threshold_key: None,
+            good: None,
+            info: None,
+            warning: None,
+            critical: None,
+            dbus_name: None,
+            clear_after: None,
             shell: env::var("SHELL").unwrap_or_else(|_| "sh".to_owned()),
         }
     }
@@ -83,15 +161,29 @@ impl ConfigBlock for Custom {
     ) -> Result<Self> {
         let mut custom = Custom {
             id,
+            base_interval: block_config.interval.clone(),
             update_interval: block_config.interval,
             command: None,
             on_click: None,
             cycle: None,
             signal: None,
-            tx_update_request: tx,
+            tx_update_request: tx.clone(),
             json: block_config.json,
             hide_when_empty: block_config.hide_when_empty,
             shell: block_config.shell,
+            persistent: block_config.persistent,
+            watch_output: None,
+            timeout: block_config.timeout.map(Duration::from_secs),
+            retry_base: Duration::from_secs(block_config.retry_base),
+            retry_max: Duration::from_secs(block_config.retry_max),
+            attempt: 0,
+            format: block_config.format,
+            threshold_key: block_config.threshold_key,
+            good: block_config.good,
+            info: block_config.info,
+            warning: block_config.warning,
+            critical: block_config.critical,
+            pushed: None,
             shared_config,
         };
 
@@ -107,6 +199,13 @@ impl ConfigBlock for Custom {
             ));
         }
 
+        if custom.persistent && block_config.cycle.is_some() {
+            return Err(BlockError(
+                "custom".to_string(),
+                "`persistent` and `cycle` are mutually exclusive".to_string(),
+            ));
+        }
+
         if let Some(cycle) = block_config.cycle {
             custom.cycle = Some(cycle.into_iter().cycle().peekable());
             return Ok(custom);
@@ -116,6 +215,28 @@ impl ConfigBlock for Custom {
             custom.command = Some(command)
         };
 
+        if custom.persistent {
+            let command_str = custom.command.clone().unwrap_or_default();
+            let output = Arc::new(Mutex::new(String::new()));
+            custom.watch_output = Some(output.clone());
+            spawn_persistent_command(
+                custom.shell.clone(),
+                command_str,
+                output,
+                tx.clone(),
+                id,
+                custom.retry_base,
+                custom.retry_max,
+            );
+        }
+
+        if let Some(bus_name) = block_config.dbus_name {
+            let pushed = Arc::new(Mutex::new(PushedState::default()));
+            custom.pushed = Some(pushed.clone());
+            let clear_after = block_config.clear_after.map(Duration::from_secs);
+            spawn_dbus_listener(bus_name, pushed, clear_after, tx, id);
+        }
+
         Ok(custom)
     }
 
@@ -124,21 +245,387 @@ impl ConfigBlock for Custom {
     }
 }
 
+/// Doubles `attempt`'s delay (capped at `max`) and adds a little jitter,
+/// the same shape as `Custom::backoff` but usable from a free function that
+/// doesn't have a `Custom` to borrow.
+fn persistent_backoff(attempt: &mut u32, base: Duration, max: Duration) -> Duration {
+    let exp = base.saturating_mul(1 << (*attempt).min(16));
+    *attempt = attempt.saturating_add(1);
+
+    let jitter_ms = rand::thread_rng().gen_range(0..250);
+    (exp + Duration::from_millis(jitter_ms)).min(max)
+}
+
+/// Spawns `command` as a long-running child and keeps a shared buffer
+/// updated with its latest line of output, nudging the scheduler every
+/// time a new line arrives. If the child exits it is respawned so the
+/// block keeps following the command for as long as it lives.
+fn spawn_persistent_command(
+    shell: String,
+    command: String,
+    output: Arc<Mutex<String>>,
+    tx_update_request: Sender<Task>,
+    id: usize,
+    retry_base: Duration,
+    retry_max: Duration,
+) {
+    tokio::spawn(async move {
+        let mut attempt: u32 = 0;
+
+        loop {
+            let child = Command::new(&shell)
+                .args(&["-c", &command])
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .spawn();
+
+            let mut child = match child {
+                Ok(child) => child,
+                Err(_) => {
+                    // Nothing sensible to display or retry against here yet;
+                    // back off so we don't spin on a broken shell.
+                    tokio::time::sleep(persistent_backoff(&mut attempt, retry_base, retry_max)).await;
+                    continue;
+                }
+            };
+
+            let stdout = match child.stdout.take() {
+                Some(stdout) => stdout,
+                None => {
+                    // Wait on the child so it doesn't linger as a zombie,
+                    // then back off the same as any other failed attempt.
+                    let _ = child.wait().await;
+                    tokio::time::sleep(persistent_backoff(&mut attempt, retry_base, retry_max)).await;
+                    continue;
+                }
+            };
+
+            let mut lines = BufReader::new(stdout).lines();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                attempt = 0;
+                *output.lock().unwrap() = line;
+                if tx_update_request
+                    .send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    })
+                    .is_err()
+                {
+                    return;
+                }
+            }
+
+            // The child exited (or its stdout closed); restart it so
+            // `persistent` commands that crash keep being followed, but
+            // back off first so a command that isn't actually a long-running
+            // follower (or an empty `command`) doesn't re-fork in a tight
+            // loop.
+            let _ = child.wait().await;
+            tokio::time::sleep(persistent_backoff(&mut attempt, retry_base, retry_max)).await;
+        }
+    });
+}
+
+/// Content pushed to the block over DBus, along with when it should be
+/// cleared if nothing newer comes in.
+struct PushedState {
+    has_value: bool,
+    icon: String,
+    state: State,
+    text: String,
+    clear_at: Option<Instant>,
+}
+
+impl Default for PushedState {
+    fn default() -> Self {
+        Self {
+            has_value: false,
+            icon: String::new(),
+            state: State::Idle,
+            text: String::new(),
+            clear_at: None,
+        }
+    }
+}
+
+fn parse_pushed_state(raw: &str) -> State {
+    match raw.to_ascii_lowercase().as_str() {
+        "good" => State::Good,
+        "info" => State::Info,
+        "warning" => State::Warning,
+        "critical" => State::Critical,
+        _ => State::Idle,
+    }
+}
+
+/// Nudges the scheduler at `clear_at` so a pushed update with `clear_after`
+/// set disappears on time rather than lingering until the next `command`
+/// poll (or forever, for a push-only block with no poll source at all).
+/// Clears `pushed` itself too, as long as a newer push hasn't replaced it
+/// in the meantime.
+fn schedule_clear(
+    runtime: &tokio::runtime::Handle,
+    pushed: Arc<Mutex<PushedState>>,
+    clear_at: Instant,
+    tx_update_request: Sender<Task>,
+    id: usize,
+) {
+    runtime.spawn(async move {
+        tokio::time::sleep_until(tokio::time::Instant::from_std(clear_at)).await;
+
+        let mut guard = pushed.lock().unwrap();
+        if guard.clear_at == Some(clear_at) {
+            *guard = PushedState::default();
+        }
+        drop(guard);
+
+        let _ = tx_update_request.send(Task {
+            id,
+            update_time: Instant::now(),
+        });
+    });
+}
+
+/// Registers `bus_name` on the session bus and exposes an `Update(icon,
+/// state, text)` method on `/Update` that external programs can call to
+/// push content into the block, the way `custom_dbus` already does. Runs
+/// on its own thread since the `dbus` crate's blocking API is simplest for
+/// a handler that just needs to sit and wait for method calls; the Tokio
+/// `Handle` is captured up front so that thread can still schedule the
+/// `clear_after` wakeup on the async runtime.
+fn spawn_dbus_listener(
+    bus_name: String,
+    pushed: Arc<Mutex<PushedState>>,
+    clear_after: Option<Duration>,
+    tx_update_request: Sender<Task>,
+    id: usize,
+) {
+    let runtime = tokio::runtime::Handle::current();
+
+    std::thread::spawn(move || {
+        let conn = match Connection::new_session() {
+            Ok(conn) => conn,
+            Err(_) => return,
+        };
+
+        if conn.request_name(&bus_name, false, true, false).is_err() {
+            return;
+        }
+
+        let mut cr = Crossroads::new();
+        let iface_token = cr.register("rs.i3status.Custom", |b| {
+            b.method(
+                "Update",
+                ("icon", "state", "text"),
+                (),
+                move |_, _, (icon, state, text): (String, String, String)| {
+                    let mut guard = pushed.lock().unwrap();
+                    guard.has_value = true;
+                    guard.icon = icon;
+                    guard.state = parse_pushed_state(&state);
+                    guard.text = text;
+                    let clear_at = clear_after.map(|d| Instant::now() + d);
+                    guard.clear_at = clear_at;
+                    drop(guard);
+
+                    let _ = tx_update_request.send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    });
+
+                    if let Some(clear_at) = clear_at {
+                        schedule_clear(
+                            &runtime,
+                            pushed.clone(),
+                            clear_at,
+                            tx_update_request.clone(),
+                            id,
+                        );
+                    }
+
+                    Ok(())
+                },
+            );
+        });
+        cr.insert("/Update", &[iface_token], ());
+
+        // Blocks forever, dispatching incoming method calls to `cr`.
+        let _ = cr.serve(&conn);
+    });
+}
+
+impl Custom {
+    /// Doubles the retry delay (capped at `retry_max`) and adds a little
+    /// jitter so a fleet of identical blocks doesn't retry in lockstep.
+    fn backoff(&mut self) -> Duration {
+        let exp = self.retry_base.saturating_mul(1 << self.attempt.min(16));
+        self.attempt = self.attempt.saturating_add(1);
+
+        let jitter_ms = rand::thread_rng().gen_range(0..250);
+        (exp + Duration::from_millis(jitter_ms)).min(self.retry_max)
+    }
+
+    /// Picks a `State` from `threshold_key`'s value in `values`, the way
+    /// the `github` block maps counts to a state through its own
+    /// thresholds.
+    fn threshold_state(&self, values: &HashMap<String, serde_json::Value>) -> State {
+        let value = self
+            .threshold_key
+            .as_ref()
+            .and_then(|key| values.get(key))
+            .and_then(|value| value.as_f64());
+
+        let value = match value {
+            Some(value) => value,
+            None => return State::Idle,
+        };
+
+        if self.critical.map_or(false, |t| value >= t) {
+            State::Critical
+        } else if self.warning.map_or(false, |t| value >= t) {
+            State::Warning
+        } else if self.info.map_or(false, |t| value >= t) {
+            State::Info
+        } else if self.good.map_or(false, |t| value >= t) {
+            State::Good
+        } else {
+            State::Idle
+        }
+    }
+
+    /// Takes the current pushed content, if any, clearing it first if its
+    /// `clear_after` has elapsed.
+    fn take_pushed_override(&self) -> Option<(String, State, String)> {
+        let pushed = self.pushed.as_ref()?;
+        let mut guard = pushed.lock().unwrap();
+
+        if let Some(clear_at) = guard.clear_at {
+            if Instant::now() >= clear_at {
+                *guard = PushedState::default();
+            }
+        }
+
+        guard
+            .has_value
+            .then(|| (guard.icon.clone(), guard.state, guard.text.clone()))
+    }
+
+    /// Builds the single widget a pushed DBus update renders as.
+    fn widget_from_pushed(
+        &self,
+        (icon, state, text): (String, State, String),
+    ) -> Result<Vec<Box<dyn I3BarWidget>>> {
+        let mut widget = TextWidget::new(self.id(), 0, self.shared_config.clone());
+
+        if !icon.is_empty() {
+            widget.set_icon(&icon)?;
+        }
+        widget.set_state(state);
+
+        if text.is_empty() && self.hide_when_empty {
+            Ok(Vec::new())
+        } else {
+            widget.set_text(text);
+            Ok(vec![Box::new(widget)])
+        }
+    }
+
+    /// Turns one JSON `Output` into a widget, honoring `hide_when_empty`
+    /// for that element alone.
+    fn build_widget(&self, index: usize, output: Output) -> Result<Option<Box<dyn I3BarWidget>>> {
+        let mut widget = TextWidget::new(self.id(), index, self.shared_config.clone());
+
+        if !output.icon.is_empty() {
+            widget.set_icon(&output.icon)?;
+        }
+
+        let state = output
+            .state
+            .unwrap_or_else(|| self.threshold_state(&output.values));
+        widget.set_state(state);
+
+        let (text, short_text) = self.format.render(&template_values(&output))?;
+        if text.is_empty() && self.hide_when_empty {
+            return Ok(None);
+        }
+
+        widget.set_text(text);
+        if let Some(short_text) = short_text {
+            widget.set_short_text(short_text);
+        }
+        Ok(Some(Box::new(widget)))
+    }
+}
+
 fn default_icon() -> String {
     String::from("")
 }
 
-fn default_state() -> State {
-    State::Idle
+/// Falls back to rendering plain `{text}` so existing `json = true`
+/// configs keep showing the script's `text` field without having to spell
+/// out a `format`.
+fn default_format() -> FormatTemplate {
+    "{text}".parse().expect("default format template is valid")
+}
+
+/// Turns a script-provided JSON value into the `formatting::value::Value`
+/// a `FormatTemplate` placeholder expects, the same conversion the `github`
+/// block does for its own counts so numbers format (units, precision) like
+/// everywhere else instead of however `serde_json` happens to print them.
+fn json_to_value(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Number(n) => n
+            .as_f64()
+            .map(Value::from_float)
+            .unwrap_or_else(|| Value::from_string(n.to_string())),
+        serde_json::Value::String(s) => Value::from_string(s.clone()),
+        other => Value::from_string(other.to_string()),
+    }
+}
+
+/// Builds the placeholder map passed to `FormatTemplate::render`: every
+/// entry of `values`, plus `text` itself so `{text}` keeps working for
+/// scripts that don't use `values` at all.
+fn template_values(output: &Output) -> HashMap<String, Value> {
+    let mut values: HashMap<String, Value> = output
+        .values
+        .iter()
+        .map(|(key, value)| (key.clone(), json_to_value(value)))
+        .collect();
+    values
+        .entry("text".to_string())
+        .or_insert_with(|| Value::from_string(output.text.clone()));
+    values
 }
 
 #[derive(Deserialize)]
 struct Output {
     #[serde(default = "default_icon")]
     icon: String,
-    #[serde(default = "default_state")]
-    state: State,
+    state: Option<State>,
+    #[serde(default)]
     text: String,
+    #[serde(default)]
+    values: HashMap<String, serde_json::Value>,
+}
+
+/// A single JSON payload can either be one `Output` object, or an array of
+/// them when a script wants to render several adjacent segments at once.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Outputs {
+    Many(Vec<Output>),
+    One(Output),
+}
+
+impl Outputs {
+    fn into_vec(self) -> Vec<Output> {
+        match self {
+            Outputs::Many(outputs) => outputs,
+            Outputs::One(output) => vec![output],
+        }
+    }
 }
 
 #[async_trait(?Send)]
@@ -148,44 +635,113 @@ impl Block for Custom {
     }
 
     async fn render(&'_ mut self) -> Result<Vec<Box<dyn I3BarWidget>>> {
-        let mut widget = TextWidget::new(self.id(), 0, self.shared_config.clone());
-
-        let command_str = self
-            .cycle
-            .as_mut()
-            .map(|c| c.peek().cloned().unwrap_or_else(|| "".to_owned()))
-            .or_else(|| self.command.clone())
-            .unwrap_or_else(|| "".to_owned());
-
-        let raw_output = Command::new(&self.shell)
-            .args(&["-c", &command_str])
-            .output()
-            .await
-            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_owned())
-            .unwrap_or_else(|e| e.to_string());
-
-        let text = {
-            if self.json {
-                let output: Output = serde_json::from_str(&*raw_output).map_err(|e| {
-                    BlockError("custom".to_string(), format!("Error parsing JSON: {}", e))
-                })?;
+        let pushed_override = self.take_pushed_override();
+
+        // `command`/`cycle`/`persistent` are the polling-based sources; if
+        // none are configured this block only ever has pushed content to
+        // show, so there's nothing to fall back to.
+        let has_poll_source = self.command.is_some() || self.cycle.is_some() || self.persistent;
+
+        if !has_poll_source {
+            return match pushed_override {
+                Some(over) => self.widget_from_pushed(over),
+                None => Ok(Vec::new()),
+            };
+        }
 
-                if !output.icon.is_empty() {
-                    widget.set_icon(&output.icon)?;
+        let (raw_output, failed) = if let Some(ref output) = self.watch_output {
+            let buf = output.lock().unwrap().clone();
+            if buf.is_empty() {
+                // The persistent child hasn't printed its first line yet;
+                // there's nothing to show (and nothing to parse as JSON),
+                // unless a pushed update already has something to say.
+                return match pushed_override {
+                    Some(over) => self.widget_from_pushed(over),
+                    None => Ok(Vec::new()),
+                };
+            }
+            (buf, false)
+        } else {
+            let command_str = self
+                .cycle
+                .as_mut()
+                .map(|c| c.peek().cloned().unwrap_or_else(|| "".to_owned()))
+                .or_else(|| self.command.clone())
+                .unwrap_or_else(|| "".to_owned());
+
+            let child_output = Command::new(&self.shell).args(&["-c", &command_str]).output();
+            let output = match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, child_output).await,
+                None => Ok(child_output.await),
+            };
+
+            match output {
+                Ok(Ok(output)) if output.status.success() => {
+                    self.attempt = 0;
+                    self.update_interval = self.base_interval.clone();
+                    (String::from_utf8_lossy(&output.stdout).trim().to_owned(), false)
+                }
+                Ok(Ok(output)) => {
+                    self.update_interval = Update::Every(self.backoff());
+                    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+                    let text = if stderr.is_empty() {
+                        format!("exited with {}", output.status)
+                    } else {
+                        stderr
+                    };
+                    (text, true)
                 }
+                Ok(Err(e)) => {
+                    self.update_interval = Update::Every(self.backoff());
+                    (e.to_string(), true)
+                }
+                Err(_) => {
+                    self.update_interval = Update::Every(self.backoff());
+                    let secs = self.timeout.map(|t| t.as_secs()).unwrap_or_default();
+                    (format!("timed out after {}s", secs), true)
+                }
+            }
+        };
+
+        let widgets = if failed {
+            let mut widget = TextWidget::new(self.id(), 0, self.shared_config.clone());
+            widget.set_state(State::Critical);
 
-                widget.set_state(output.state);
-                output.text
+            if raw_output.is_empty() && self.hide_when_empty {
+                Ok(Vec::new())
             } else {
-                raw_output
+                widget.set_text(raw_output);
+                Ok(vec![Box::new(widget)])
             }
-        };
+        } else if self.json {
+            let outputs: Outputs = serde_json::from_str(&*raw_output).map_err(|e| {
+                BlockError("custom".to_string(), format!("Error parsing JSON: {}", e))
+            })?;
 
-        if text.is_empty() && self.hide_when_empty {
-            Ok(Vec::new())
+            outputs
+                .into_vec()
+                .into_iter()
+                .enumerate()
+                .map(|(i, output)| self.build_widget(i, output))
+                .collect::<Result<Vec<_>>>()
+                .map(|widgets| widgets.into_iter().flatten().collect())
         } else {
-            widget.set_text(text);
-            Ok(vec![Box::new(widget)])
+            let mut widget = TextWidget::new(self.id(), 0, self.shared_config.clone());
+
+            if raw_output.is_empty() && self.hide_when_empty {
+                Ok(Vec::new())
+            } else {
+                widget.set_text(raw_output);
+                Ok(vec![Box::new(widget)])
+            }
+        };
+
+        // A pushed update still wins visually over a polled `command`, but
+        // polling above keeps running so `attempt`/`update_interval` stay
+        // live for the "in addition to" case.
+        match pushed_override {
+            Some(over) => self.widget_from_pushed(over),
+            None => widgets,
         }
     }
 
@@ -228,3 +784,77 @@ impl Block for Custom {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_custom(
+        good: Option<f64>,
+        info: Option<f64>,
+        warning: Option<f64>,
+        critical: Option<f64>,
+    ) -> Custom {
+        let (tx, _rx) = crossbeam_channel::unbounded();
+        Custom {
+            id: 0,
+            base_interval: Update::Every(Duration::from_secs(10)),
+            update_interval: Update::Every(Duration::from_secs(10)),
+            command: None,
+            on_click: None,
+            cycle: None,
+            signal: None,
+            tx_update_request: tx,
+            json: false,
+            hide_when_empty: false,
+            shell: "sh".to_string(),
+            persistent: false,
+            watch_output: None,
+            timeout: None,
+            retry_base: Duration::from_secs(1),
+            retry_max: Duration::from_secs(300),
+            attempt: 0,
+            format: default_format(),
+            threshold_key: Some("value".to_string()),
+            good,
+            info,
+            warning,
+            critical,
+            pushed: None,
+            shared_config: SharedConfig::default(),
+        }
+    }
+
+    fn values(n: f64) -> HashMap<String, serde_json::Value> {
+        let mut map = HashMap::new();
+        map.insert("value".to_string(), serde_json::json!(n));
+        map
+    }
+
+    #[test]
+    fn threshold_state_picks_highest_matching_tier() {
+        let custom = test_custom(Some(0.0), Some(10.0), Some(20.0), Some(30.0));
+
+        assert_eq!(custom.threshold_state(&values(-1.0)), State::Idle);
+        assert_eq!(custom.threshold_state(&values(0.0)), State::Good);
+        assert_eq!(custom.threshold_state(&values(10.0)), State::Info);
+        assert_eq!(custom.threshold_state(&values(20.0)), State::Warning);
+        assert_eq!(custom.threshold_state(&values(30.0)), State::Critical);
+        assert_eq!(custom.threshold_state(&values(100.0)), State::Critical);
+    }
+
+    #[test]
+    fn threshold_state_missing_key_is_idle() {
+        let custom = test_custom(Some(0.0), None, None, None);
+        assert_eq!(custom.threshold_state(&HashMap::new()), State::Idle);
+    }
+
+    #[test]
+    fn outputs_accepts_single_object_or_array() {
+        let one: Outputs = serde_json::from_str(r#"{"text": "a"}"#).unwrap();
+        assert_eq!(one.into_vec().len(), 1);
+
+        let many: Outputs = serde_json::from_str(r#"[{"text": "a"}, {"text": "b"}]"#).unwrap();
+        assert_eq!(many.into_vec().len(), 2);
+    }
+}